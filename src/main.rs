@@ -1,38 +1,88 @@
 // #![allow(unused)]
 
 use rltk::{GameState, Rltk, VirtualKeyCode, RGB};
+use serde::{Deserialize, Serialize};
 use specs::prelude::*;
 use specs_derive::Component;
 use std::cmp::{max, min};
 
+mod map;
+mod monster_ai_system;
+mod rect;
+mod saveload_system;
+mod visibility_system;
+pub use map::{draw_map, Map, TileType};
+pub use rect::Rect;
+use monster_ai_system::MonsterAI;
+use saveload_system::{load_game, save_game};
+use visibility_system::VisibilitySystem;
+
+const SAVE_PATH: &str = "savegame.json";
+
 // The derive macro brings a lot of boilerplate for the Component
 // so we don't have to repeat us over and over again.
 // For example:
 // `impl Component for Position` and for Renderable and so on.
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize)]
 struct Position {
     x: i32,
     y: i32,
 }
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize)]
 struct Renderable {
     glyph: rltk::FontCharType,
+    #[serde(with = "RGBData")]
     fg: RGB,
+    #[serde(with = "RGBData")]
     bg: RGB,
 }
 
-#[derive(Component, Debug)]
+// Shim that lets `RGB` (defined in the rltk crate) ride along in a serde
+// field via `#[serde(with = "RGBData")]`, since serde can't derive
+// (de)serialization for a type it doesn't own.
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "RGB")]
+struct RGBData {
+    r: f32,
+    g: f32,
+    b: f32,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize)]
 struct Player {}
 
 // LeftMover likes to go left.
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Serialize, Deserialize)]
 struct LeftMover {}
 
+#[derive(Component)]
+struct Viewshed {
+    visible_tiles: Vec<rltk::Point>,
+    range: i32,
+    dirty: bool,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize)]
+struct Monster {}
+
+#[derive(Component, Debug, Serialize, Deserialize)]
+struct Name {
+    name: String,
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum RunState {
+    Paused,
+    Running,
+}
+
 struct State {
     // A world is an ECS (Entity Control System). It gathers the data for each
     // entity or component and does something with it.
     ecs: World,
+    runstate: RunState,
+    dispatcher: Dispatcher<'static, 'static>,
 }
 
 impl GameState for State {
@@ -40,7 +90,14 @@ impl GameState for State {
         // Clear the screen
         ctx.cls();
 
-        self.run_systems();
+        if self.runstate == RunState::Running {
+            self.run_systems();
+            self.runstate = RunState::Paused;
+        } else {
+            self.runstate = player_input(self, ctx);
+        }
+
+        draw_map(&self.ecs, ctx);
 
         let positions = self.ecs.read_storage::<Position>();
         let renderables = self.ecs.read_storage::<Renderable>();
@@ -79,8 +136,7 @@ impl<'a> System<'a> for LeftWalker {
 
 impl State {
     fn run_systems(&mut self) {
-        let mut lw = LeftWalker {};
-        lw.run_now(&self.ecs);
+        self.dispatcher.dispatch(&self.ecs);
         self.ecs.maintain();
     }
 }
@@ -89,24 +145,67 @@ fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
     // We need write access to the Position and the Player structures.
     let mut positions = ecs.write_storage::<Position>();
     let mut players = ecs.write_storage::<Player>();
+    let mut viewsheds = ecs.write_storage::<Viewshed>();
+    let map = ecs.fetch::<Map>();
+
+    for (_player, pos, viewshed) in (&mut players, &mut positions, &mut viewsheds).join() {
+        let destination_idx = map.xy_idx(pos.x + delta_x, pos.y + delta_y);
+        if map.tiles[destination_idx] != TileType::Wall {
+            pos.x = min(79, max(0, pos.x + delta_x));
+            pos.y = min(49, max(0, pos.y + delta_y));
+            viewshed.dirty = true;
 
-    //
-    for (_player, pos) in (&mut players, &mut positions).join() {
-        pos.x = min(79, max(0, pos.x + delta_x));
-        pos.y = min(49, max(0, pos.y + delta_y));
+            let mut ppos = ecs.write_resource::<rltk::Point>();
+            ppos.x = pos.x;
+            ppos.y = pos.y;
+        }
     }
 }
 
-// Player movement
-fn player_input(gs: &mut State, ctx: Rltk) {
+// Player movement. Returns `Running` only when a key was actually consumed,
+// so idle frames don't advance the rest of the world.
+fn player_input(gs: &mut State, ctx: &Rltk) -> RunState {
     match ctx.key {
-        None => {}
+        None => RunState::Paused,
         Some(key) => match key {
-            VirtualKeyCode::H => try_move_player(-1, 0, &mut gs.ecs),
-            VirtualKeyCode::L => try_move_player(1, 0, &mut gs.ecs),
-            VirtualKeyCode::K => try_move_player(0, -1, &mut gs.ecs),
-            VirtualKeyCode::J => try_move_player(0, 1, &mut gs.ecs),
-            _ => {}
+            VirtualKeyCode::H => {
+                try_move_player(-1, 0, &mut gs.ecs);
+                RunState::Running
+            }
+            VirtualKeyCode::L => {
+                try_move_player(1, 0, &mut gs.ecs);
+                RunState::Running
+            }
+            VirtualKeyCode::K => {
+                try_move_player(0, -1, &mut gs.ecs);
+                RunState::Running
+            }
+            VirtualKeyCode::J => {
+                try_move_player(0, 1, &mut gs.ecs);
+                RunState::Running
+            }
+            VirtualKeyCode::S => {
+                let result = save_game(&gs.ecs).and_then(|json| {
+                    std::fs::write(SAVE_PATH, json)
+                        .map_err(|e| format!("failed to write save game: {}", e))
+                });
+                match result {
+                    Ok(()) => rltk::console::log("Game saved"),
+                    Err(e) => rltk::console::log(format!("Save failed: {}", e)),
+                }
+                RunState::Paused
+            }
+            VirtualKeyCode::O => {
+                match std::fs::read_to_string(SAVE_PATH)
+                    .map_err(|e| format!("failed to read save game: {}", e))
+                    .and_then(|json| load_game(&mut gs.ecs, &json))
+                {
+                    Ok(()) => rltk::console::log("Game loaded"),
+                    Err(e) => rltk::console::log(format!("Load failed: {}", e)),
+                }
+                RunState::Paused
+            }
+            _ => RunState::Paused,
         },
     }
 }
@@ -116,7 +215,19 @@ fn main() -> rltk::BError {
 
     let context = RltkBuilder::simple80x50().with_title("Rustlike").build()?;
 
-    let mut gs = State { ecs: World::new() };
+    // Visibility must run before the AI can check what's in view, and the AI's
+    // movement decisions must land before LeftWalker shuffles positions again.
+    let dispatcher = DispatcherBuilder::new()
+        .with(VisibilitySystem {}, "visibility_system", &[])
+        .with(MonsterAI {}, "monster_ai", &["visibility_system"])
+        .with(LeftWalker {}, "left_walker", &["monster_ai"])
+        .build();
+
+    let mut gs = State {
+        ecs: World::new(),
+        runstate: RunState::Running,
+        dispatcher,
+    };
 
     // Here we tell our `World` to take a look at everything we gave it, namely
     // everything that implements a `Component`, and create storages for those.
@@ -124,6 +235,12 @@ fn main() -> rltk::BError {
     gs.ecs.register::<Renderable>();
     gs.ecs.register::<LeftMover>();
     gs.ecs.register::<Player>();
+    gs.ecs.register::<Viewshed>();
+    gs.ecs.register::<Monster>();
+    gs.ecs.register::<Name>();
+
+    let map = Map::new_map_rooms_and_corridors();
+    let (player_x, player_y) = map.rooms[0].center();
 
     gs.ecs
         // We create an entity, it's like an identification number, and
@@ -131,13 +248,21 @@ fn main() -> rltk::BError {
         .create_entity()
         // Then we give the entity any combination of components we want by
         // using `.with()`.
-        .with(Position { x: 40, y: 25 })
+        .with(Position {
+            x: player_x,
+            y: player_y,
+        })
         .with(Renderable {
             glyph: rltk::to_cp437('@'),
             fg: RGB::named(rltk::YELLOW),
             bg: RGB::named(rltk::BLACK),
         })
         .with(Player {})
+        .with(Viewshed {
+            visible_tiles: Vec::new(),
+            range: 8,
+            dirty: true,
+        })
         .build();
 
     for i in 0..10 {
@@ -153,5 +278,37 @@ fn main() -> rltk::BError {
             .build();
     }
 
+    let mut rng = rltk::RandomNumberGenerator::new();
+    for (i, room) in map.rooms.iter().skip(1).enumerate() {
+        let (x, y) = room.center();
+
+        let (glyph, name) = match rng.roll_dice(1, 2) {
+            1 => (rltk::to_cp437('g'), "Goblin"),
+            _ => (rltk::to_cp437('o'), "Orc"),
+        };
+
+        gs.ecs
+            .create_entity()
+            .with(Position { x, y })
+            .with(Renderable {
+                glyph,
+                fg: RGB::named(rltk::RED),
+                bg: RGB::named(rltk::BLACK),
+            })
+            .with(Viewshed {
+                visible_tiles: Vec::new(),
+                range: 8,
+                dirty: true,
+            })
+            .with(Monster {})
+            .with(Name {
+                name: format!("{} #{}", name, i),
+            })
+            .build();
+    }
+
+    gs.ecs.insert(rltk::Point::new(player_x, player_y));
+    gs.ecs.insert(map);
+
     rltk::main_loop(context, gs)
 }
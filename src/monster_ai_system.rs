@@ -0,0 +1,40 @@
+use super::{Map, Monster, Name, Position, TileType, Viewshed};
+use rltk::Point;
+use specs::prelude::*;
+
+pub struct MonsterAI {}
+
+impl<'a> System<'a> for MonsterAI {
+    type SystemData = (
+        ReadExpect<'a, Point>,
+        ReadExpect<'a, Map>,
+        WriteStorage<'a, Viewshed>,
+        ReadStorage<'a, Monster>,
+        ReadStorage<'a, Name>,
+        WriteStorage<'a, Position>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (player_pos, map, mut viewshed, monster, name, mut position) = data;
+
+        for (viewshed, _monster, name, pos) in
+            (&mut viewshed, &monster, &name, &mut position).join()
+        {
+            if !viewshed.visible_tiles.contains(&*player_pos) {
+                continue;
+            }
+
+            rltk::console::log(format!("{} shouts insults", name.name));
+
+            // Take one step toward the player, as long as it isn't a wall.
+            let dx = (player_pos.x - pos.x).signum();
+            let dy = (player_pos.y - pos.y).signum();
+            let idx = map.xy_idx(pos.x + dx, pos.y + dy);
+            if map.tiles[idx] != TileType::Wall {
+                pos.x += dx;
+                pos.y += dy;
+                viewshed.dirty = true;
+            }
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use super::{LeftMover, Monster, Name, Player, Position, Renderable, Viewshed};
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+
+// `Viewshed.visible_tiles` is a `Vec<rltk::Point>` recomputed every time the
+// `dirty` flag is set, so there's nothing worth persisting beyond `range` —
+// on load we mark the viewshed dirty and let `VisibilitySystem` rebuild it.
+#[derive(Serialize, Deserialize)]
+struct ViewshedData {
+    range: i32,
+}
+
+// One of an entity's present components, tagged by variant so `load_game` can
+// tell a `Position` entry from a `Renderable` one without guessing.
+#[derive(Serialize, Deserialize)]
+enum ComponentData {
+    Position(Position),
+    Renderable(Renderable),
+    Player(Player),
+    LeftMover(LeftMover),
+    Viewshed(ViewshedData),
+    Monster(Monster),
+    Name(Name),
+}
+
+pub fn save_game(ecs: &World) -> Result<String, String> {
+    let entities = ecs.entities();
+    let positions = ecs.read_storage::<Position>();
+    let renderables = ecs.read_storage::<Renderable>();
+    let players = ecs.read_storage::<Player>();
+    let left_movers = ecs.read_storage::<LeftMover>();
+    let viewsheds = ecs.read_storage::<Viewshed>();
+    let monsters = ecs.read_storage::<Monster>();
+    let names = ecs.read_storage::<Name>();
+
+    let mut entity_records: Vec<Vec<ComponentData>> = Vec::new();
+    for entity in entities.join() {
+        let mut components = Vec::new();
+        if let Some(p) = positions.get(entity) {
+            components.push(ComponentData::Position(Position { x: p.x, y: p.y }));
+        }
+        if let Some(r) = renderables.get(entity) {
+            components.push(ComponentData::Renderable(Renderable {
+                glyph: r.glyph,
+                fg: r.fg,
+                bg: r.bg,
+            }));
+        }
+        if players.get(entity).is_some() {
+            components.push(ComponentData::Player(Player {}));
+        }
+        if left_movers.get(entity).is_some() {
+            components.push(ComponentData::LeftMover(LeftMover {}));
+        }
+        if let Some(v) = viewsheds.get(entity) {
+            components.push(ComponentData::Viewshed(ViewshedData { range: v.range }));
+        }
+        if monsters.get(entity).is_some() {
+            components.push(ComponentData::Monster(Monster {}));
+        }
+        if let Some(n) = names.get(entity) {
+            components.push(ComponentData::Name(Name {
+                name: n.name.clone(),
+            }));
+        }
+        entity_records.push(components);
+    }
+
+    serde_json::to_string(&entity_records)
+        .map_err(|e| format!("failed to serialize save game: {}", e))
+}
+
+pub fn load_game(ecs: &mut World, json: &str) -> Result<(), String> {
+    let entity_records: Vec<Vec<ComponentData>> = serde_json::from_str(json)
+        .map_err(|e| format!("failed to deserialize save game: {}", e))?;
+
+    let to_delete: Vec<Entity> = ecs.entities().join().collect();
+    for entity in to_delete {
+        ecs.delete_entity(entity)
+            .map_err(|e| format!("failed to clear world: {}", e))?;
+    }
+
+    for components in entity_records {
+        let mut builder = ecs.create_entity();
+        let mut is_player = false;
+        let mut player_pos: Option<(i32, i32)> = None;
+        for component in &components {
+            if let ComponentData::Player(_) = component {
+                is_player = true;
+            }
+            if let ComponentData::Position(p) = component {
+                player_pos = Some((p.x, p.y));
+            }
+        }
+
+        for component in components {
+            builder = match component {
+                ComponentData::Position(c) => builder.with(c),
+                ComponentData::Renderable(c) => builder.with(c),
+                ComponentData::Player(c) => builder.with(c),
+                ComponentData::LeftMover(c) => builder.with(c),
+                ComponentData::Viewshed(data) => builder.with(Viewshed {
+                    visible_tiles: Vec::new(),
+                    range: data.range,
+                    dirty: true,
+                }),
+                ComponentData::Monster(c) => builder.with(c),
+                ComponentData::Name(c) => builder.with(c),
+            };
+        }
+        builder.build();
+
+        // Keep the shared player-position resource (used for monster aggro)
+        // in sync with the reloaded player entity.
+        if is_player {
+            if let Some((x, y)) = player_pos {
+                ecs.insert(rltk::Point::new(x, y));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rltk::RGB;
+
+    fn new_test_world() -> World {
+        let mut world = World::new();
+        world.register::<Position>();
+        world.register::<Renderable>();
+        world.register::<Player>();
+        world.register::<LeftMover>();
+        world.register::<Viewshed>();
+        world.register::<Monster>();
+        world.register::<Name>();
+        world.insert(rltk::Point::new(0, 0));
+        world
+    }
+
+    #[test]
+    fn round_trip_preserves_monster_components() {
+        let mut world = new_test_world();
+        world
+            .create_entity()
+            .with(Position { x: 5, y: 6 })
+            .with(Renderable {
+                glyph: rltk::to_cp437('g'),
+                fg: RGB::named(rltk::RED),
+                bg: RGB::named(rltk::BLACK),
+            })
+            .with(Viewshed {
+                visible_tiles: Vec::new(),
+                range: 8,
+                dirty: true,
+            })
+            .with(Monster {})
+            .with(Name {
+                name: "Goblin #0".to_string(),
+            })
+            .build();
+
+        let json = save_game(&world).expect("save should succeed");
+        load_game(&mut world, &json).expect("load should succeed");
+
+        let entities = world.entities();
+        let monsters = world.read_storage::<Monster>();
+        let names = world.read_storage::<Name>();
+        let viewsheds = world.read_storage::<Viewshed>();
+
+        let (_, name, viewshed) = (&entities, &monsters, &names, &viewsheds)
+            .join()
+            .map(|(e, _, n, v)| (e, n, v))
+            .next()
+            .expect("monster entity should survive a save/load round trip");
+
+        assert_eq!(name.name, "Goblin #0");
+        assert_eq!(viewshed.range, 8);
+        assert!(viewshed.dirty);
+    }
+
+    #[test]
+    fn round_trip_resyncs_player_position_resource() {
+        let mut world = new_test_world();
+        world
+            .create_entity()
+            .with(Position { x: 10, y: 20 })
+            .with(Renderable {
+                glyph: rltk::to_cp437('@'),
+                fg: RGB::named(rltk::YELLOW),
+                bg: RGB::named(rltk::BLACK),
+            })
+            .with(Player {})
+            .with(Viewshed {
+                visible_tiles: Vec::new(),
+                range: 8,
+                dirty: true,
+            })
+            .build();
+
+        let json = save_game(&world).expect("save should succeed");
+        load_game(&mut world, &json).expect("load should succeed");
+
+        let ppos = world.fetch::<rltk::Point>();
+        assert_eq!(*ppos, rltk::Point::new(10, 20));
+    }
+
+    #[test]
+    fn load_game_reports_corrupt_save_without_panicking() {
+        let mut world = new_test_world();
+        world
+            .create_entity()
+            .with(Position { x: 1, y: 1 })
+            .with(Player {})
+            .build();
+
+        let result = load_game(&mut world, "not valid json");
+
+        assert!(result.is_err());
+    }
+}
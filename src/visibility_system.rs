@@ -0,0 +1,205 @@
+use super::{Map, Player, Position, TileType, Viewshed};
+use rltk::Point;
+use specs::prelude::*;
+
+pub struct VisibilitySystem {}
+
+impl<'a> System<'a> for VisibilitySystem {
+    type SystemData = (
+        WriteExpect<'a, Map>,
+        Entities<'a>,
+        WriteStorage<'a, Viewshed>,
+        WriteStorage<'a, Position>,
+        ReadStorage<'a, Player>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut map, entities, mut viewshed, pos, player) = data;
+
+        for (ent, viewshed, pos) in (&entities, &mut viewshed, &pos).join() {
+            if !viewshed.dirty {
+                continue;
+            }
+            viewshed.dirty = false;
+            viewshed.visible_tiles.clear();
+            viewshed.visible_tiles = compute_fov(&map, Point::new(pos.x, pos.y), viewshed.range);
+            viewshed
+                .visible_tiles
+                .retain(|p| p.x >= 0 && p.x < map.width && p.y >= 0 && p.y < map.height);
+
+            // If this is the player, reveal what they can see.
+            if player.get(ent).is_some() {
+                for v in map.visible_tiles.iter_mut() {
+                    *v = false;
+                }
+                for vis in viewshed.visible_tiles.iter() {
+                    let idx = map.xy_idx(vis.x, vis.y);
+                    map.revealed_tiles[idx] = true;
+                    map.visible_tiles[idx] = true;
+                }
+            }
+        }
+    }
+}
+
+// Recursive shadowcasting FOV, one pass per octant. For each row outward from
+// the origin we track a start/end slope pair; a tile is lit when its slope
+// range overlaps the scan's current `[start_slope, end_slope]` and it lies
+// strictly inside `range`. The first wall met in a row starts a recursive scan
+// of the next row over the slopes before that wall; the first open tile after
+// walls resumes the row with the start slope moved past them, and a row that
+// ends blocked ends the scan. The result is not symmetric: a tile can be lit
+// from the origin while the origin would not be lit from that tile.
+pub fn compute_fov(map: &Map, origin: Point, range: i32) -> Vec<Point> {
+    let mut visible = vec![origin];
+
+    // dx, dy, dx2, dy2 transforms for each of the eight octants.
+    const OCTANTS: [[i32; 4]; 8] = [
+        [1, 0, 0, 1],
+        [0, 1, 1, 0],
+        [0, -1, 1, 0],
+        [-1, 0, 0, 1],
+        [-1, 0, 0, -1],
+        [0, -1, -1, 0],
+        [0, 1, -1, 0],
+        [1, 0, 0, -1],
+    ];
+
+    for octant in OCTANTS.iter() {
+        cast_light(
+            map, origin, range, 1, 1.0, 0.0, octant[0], octant[1], octant[2], octant[3],
+            &mut visible,
+        );
+    }
+
+    visible
+}
+
+#[allow(clippy::too_many_arguments)]
+fn cast_light(
+    map: &Map,
+    origin: Point,
+    range: i32,
+    row: i32,
+    mut start_slope: f32,
+    end_slope: f32,
+    xx: i32,
+    xy: i32,
+    yx: i32,
+    yy: i32,
+    visible: &mut Vec<Point>,
+) {
+    if start_slope < end_slope {
+        return;
+    }
+
+    let mut next_start_slope = start_slope;
+    for i in row..=range {
+        let mut blocked = false;
+        let dy = -i;
+        for dx in -i..=0 {
+            let l_slope = (dx as f32 - 0.5) / (dy as f32 + 0.5);
+            let r_slope = (dx as f32 + 0.5) / (dy as f32 - 0.5);
+            if r_slope > start_slope {
+                continue;
+            }
+            if l_slope < end_slope {
+                break;
+            }
+
+            let sax = dx * xx + dy * xy;
+            let say = dx * yx + dy * yy;
+            let ax = origin.x + sax;
+            let ay = origin.y + say;
+            if ax < 0 || ax >= map.width || ay < 0 || ay >= map.height {
+                continue;
+            }
+
+            let radius2 = range * range;
+            if dx * dx + dy * dy < radius2 {
+                visible.push(Point::new(ax, ay));
+            }
+
+            let idx = map.xy_idx(ax, ay);
+            let wall = map.tiles[idx] == TileType::Wall;
+
+            if blocked {
+                if wall {
+                    next_start_slope = r_slope;
+                    continue;
+                } else {
+                    blocked = false;
+                    start_slope = next_start_slope;
+                }
+            } else if wall && i < range {
+                blocked = true;
+                cast_light(
+                    map,
+                    origin,
+                    range,
+                    i + 1,
+                    start_slope,
+                    l_slope,
+                    xx,
+                    xy,
+                    yx,
+                    yy,
+                    visible,
+                );
+                next_start_slope = r_slope;
+            }
+        }
+
+        if blocked {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blank_map(width: i32, height: i32) -> Map {
+        let size = (width * height) as usize;
+        Map {
+            tiles: vec![TileType::Floor; size],
+            rooms: Vec::new(),
+            width,
+            height,
+            revealed_tiles: vec![false; size],
+            visible_tiles: vec![false; size],
+        }
+    }
+
+    #[test]
+    fn open_room_is_fully_visible_within_range() {
+        let map = blank_map(11, 11);
+        let origin = Point::new(5, 5);
+
+        let visible = compute_fov(&map, origin, 4);
+
+        assert!(visible.contains(&Point::new(5 + 3, 5)));
+        assert!(visible.contains(&Point::new(5 - 3, 5)));
+        assert!(visible.contains(&Point::new(5, 5 + 3)));
+        assert!(visible.contains(&Point::new(5, 5 - 3)));
+    }
+
+    #[test]
+    fn wall_fully_occludes_tiles_behind_it() {
+        let mut map = blank_map(11, 11);
+        let origin = Point::new(5, 5);
+
+        // Put a wall directly east of the origin.
+        let wall_idx = map.xy_idx(6, 5);
+        map.tiles[wall_idx] = TileType::Wall;
+
+        let visible = compute_fov(&map, origin, 5);
+
+        // The wall itself is seen...
+        assert!(visible.contains(&Point::new(6, 5)));
+        // ...but nothing directly behind it is.
+        assert!(!visible.contains(&Point::new(7, 5)));
+        assert!(!visible.contains(&Point::new(8, 5)));
+    }
+}